@@ -0,0 +1,163 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use crate::device::{DeviceStatus, Status};
+use crate::error::AppError;
+use crate::price::{self, HeatingRecommendation};
+use crate::AppState;
+
+/// Envelope wrapping every API response: a `head` carrying metadata about
+/// the reading (when it was taken, which device produced it) and a `body`
+/// carrying the actual payload. Mirrors the head/body split used by the
+/// Fronius Solar API.
+#[derive(Serialize)]
+pub struct ResponseEnvelope<T> {
+    head: ResponseHead,
+    body: T,
+}
+
+#[derive(Serialize)]
+struct ResponseHead {
+    /// Unix timestamp, in seconds, of the reading this response describes.
+    timestamp: u64,
+    firmware: String,
+    serial: String,
+}
+
+/// JSON-friendly projection of [`Status`]: physical quantities are reduced
+/// to one fixed unit each (°C, V, A, W, Wh, as named in the field) as a
+/// plain `f32` so the wire format doesn't depend on `uom`'s representation.
+#[derive(Serialize)]
+pub struct StatusDto {
+    pub wassertemp_celsius: f32,
+    pub wassertemp_min_celsius: f32,
+    pub wassertemp_max_celsius: f32,
+    pub solltemp_solar_celsius: f32,
+    pub solltemp_netz_celsius: f32,
+
+    pub solarspannung_volt: f32,
+    pub solarstrom_ampere: f32,
+    pub solarleistung_watt: f32,
+
+    pub solarenergie_heute_watt_hour: f32,
+    pub solarenergie_gesamt_watt_hour: f32,
+    pub netzenergie_heute_watt_hour: f32,
+
+    pub iso_messung: u32,
+    pub geraetetemp_celsius: f32,
+    pub status: DeviceStatus,
+    pub dc_trenner: bool,
+    pub dc_relais: bool,
+    pub ac_relais: bool,
+
+    pub betriebstag: u32,
+}
+
+impl From<&Status> for StatusDto {
+    fn from(status: &Status) -> Self {
+        use uom::si::{
+            electric_current::ampere, electric_potential::volt, energy::watt_hour, power::watt,
+            thermodynamic_temperature::degree_celsius,
+        };
+
+        Self {
+            wassertemp_celsius: status.wassertemp.get::<degree_celsius>(),
+            wassertemp_min_celsius: status.wassertemp_min.get::<degree_celsius>(),
+            wassertemp_max_celsius: status.wassertemp_max.get::<degree_celsius>(),
+            solltemp_solar_celsius: status.solltemp_solar.get::<degree_celsius>(),
+            solltemp_netz_celsius: status.solltemp_netz.get::<degree_celsius>(),
+            solarspannung_volt: status.solarspannung.get::<volt>(),
+            solarstrom_ampere: status.solarstrom.get::<ampere>(),
+            solarleistung_watt: status.solarleistung.get::<watt>(),
+            solarenergie_heute_watt_hour: status.solarenergie_heute.get::<watt_hour>(),
+            solarenergie_gesamt_watt_hour: status.solarenergie_gesamt.get::<watt_hour>(),
+            netzenergie_heute_watt_hour: status.netzenergie_heute.get::<watt_hour>(),
+            iso_messung: status.iso_messung,
+            geraetetemp_celsius: status.geraetetemp.get::<degree_celsius>(),
+            status: status.status,
+            dc_trenner: status.dc_trenner,
+            dc_relais: status.dc_relais,
+            ac_relais: status.ac_relais,
+            betriebstag: status.betriebstag,
+        }
+    }
+}
+
+pub async fn status_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ResponseEnvelope<StatusDto>>, AppError> {
+    let reading = state.reading.read().await;
+    let reading = reading.as_ref().ok_or(AppError::NoReading)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .saturating_sub(reading.age().as_secs());
+
+    Ok(Json(ResponseEnvelope {
+        head: ResponseHead {
+            timestamp,
+            firmware: reading.status.firmware.clone(),
+            serial: reading.status.seriennummer.clone(),
+        },
+        body: StatusDto::from(&reading.status),
+    }))
+}
+
+/// The current spot price situation and a grid-heating recommendation,
+/// derived from the price curve and the current water temperature.
+#[derive(Serialize)]
+pub struct PriceDto {
+    pub grid_heating_cost_today_currency: f64,
+    #[serde(flatten)]
+    pub recommendation: HeatingRecommendation,
+}
+
+/// Computes the price/recommendation snapshot for the water temperature and
+/// grid setpoint of a reading. Shared between the JSON API and the HTML view
+/// so both render from the exact same numbers.
+pub async fn compute_price_dto(
+    state: &AppState,
+    wassertemp_celsius: f32,
+    solltemp_netz_celsius: f32,
+) -> Result<PriceDto, AppError> {
+    let prices = state.prices.read().await.clone();
+    if prices.is_empty() {
+        return Err(AppError::PriceUnavailable);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let recommendation = price::recommend(&prices, now, wassertemp_celsius, solltemp_netz_celsius);
+
+    let grid_heating_cost_today_currency = price::grid_heating_cost_today(&state.db, &prices, now)
+        .await
+        .map_err(AppError::Internal)?;
+
+    Ok(PriceDto {
+        grid_heating_cost_today_currency,
+        recommendation,
+    })
+}
+
+pub async fn price_handler(State(state): State<AppState>) -> Result<Json<PriceDto>, AppError> {
+    let reading = state.reading.read().await;
+    let reading = reading.as_ref().ok_or(AppError::NoReading)?;
+    let status = &reading.status;
+
+    use uom::si::thermodynamic_temperature::degree_celsius;
+    let dto = compute_price_dto(
+        &state,
+        status.wassertemp.get::<degree_celsius>(),
+        status.solltemp_netz.get::<degree_celsius>(),
+    )
+    .await?;
+
+    Ok(Json(dto))
+}
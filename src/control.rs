@@ -0,0 +1,121 @@
+use std::time::Instant;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use uom::si::f32::ThermodynamicTemperature;
+use uom::si::thermodynamic_temperature::degree_celsius;
+
+use crate::device::{self, Command};
+use crate::error::AppError;
+use crate::poller::Reading;
+use crate::AppState;
+
+/// Applies a write `command` to the device and, on success, updates the
+/// cached reading so the new setpoint/relay state shows up immediately
+/// everywhere (HTML view, `/api/status`, `/metrics`) instead of waiting for
+/// the next background poll.
+///
+/// Shared between the HTTP control routes and the MQTT command subscriber
+/// so both entry points go through the same apply-and-cache path.
+pub async fn apply_command(state: &AppState, command: Command) -> Result<device::Status, AppError> {
+    let status = tokio::task::spawn_blocking(move || device::write_command(command))
+        .await
+        .map_err(|err| AppError::Internal(err.into()))?
+        .map_err(AppError::Device)?;
+
+    *state.reading.write().await = Some(Reading {
+        status: status.clone(),
+        polled_at: Instant::now(),
+    });
+
+    Ok(status)
+}
+
+fn check_authorized(headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = std::env::var("ELWA_CONTROL_TOKEN").map_err(|_| AppError::ControlDisabled)?;
+
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| provided.as_bytes().ct_eq(expected.as_bytes()).into());
+
+    if !authorized {
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct SetTemperatureRequest {
+    pub celsius: f32,
+}
+
+#[derive(Deserialize)]
+pub struct SetRelaisRequest {
+    pub on: bool,
+}
+
+#[derive(Serialize)]
+pub struct ControlResponse {
+    pub solltemp_solar_celsius: f32,
+    pub solltemp_netz_celsius: f32,
+    pub ac_relais: bool,
+}
+
+impl From<device::Status> for ControlResponse {
+    fn from(status: device::Status) -> Self {
+        Self {
+            solltemp_solar_celsius: status.solltemp_solar.get::<degree_celsius>(),
+            solltemp_netz_celsius: status.solltemp_netz.get::<degree_celsius>(),
+            ac_relais: status.ac_relais,
+        }
+    }
+}
+
+pub async fn set_solltemp_solar(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<SetTemperatureRequest>,
+) -> Result<Json<ControlResponse>, AppError> {
+    check_authorized(&headers)?;
+
+    let command = Command::SetSolltempSolar(ThermodynamicTemperature::new::<degree_celsius>(
+        body.celsius,
+    ));
+    let status = apply_command(&state, command).await?;
+
+    Ok(Json(status.into()))
+}
+
+pub async fn set_solltemp_netz(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<SetTemperatureRequest>,
+) -> Result<Json<ControlResponse>, AppError> {
+    check_authorized(&headers)?;
+
+    let command = Command::SetSolltempNetz(ThermodynamicTemperature::new::<degree_celsius>(
+        body.celsius,
+    ));
+    let status = apply_command(&state, command).await?;
+
+    Ok(Json(status.into()))
+}
+
+pub async fn set_ac_relais(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<SetRelaisRequest>,
+) -> Result<Json<ControlResponse>, AppError> {
+    check_authorized(&headers)?;
+
+    let command = Command::SetAcRelais(body.on);
+    let status = apply_command(&state, command).await?;
+
+    Ok(Json(status.into()))
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+use strum::{EnumIter, IntoEnumIterator};
+use uom::si::{
+    electric_current::ampere, electric_potential::volt, energy::watt_hour, f32::*, power::watt,
+    thermodynamic_temperature::degree_celsius,
+};
+
+use crate::error::DeviceError;
+
+#[derive(EnumIter, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StatusTag {
+    Dummy0,
+    Firmware,
+    Betriebstag,
+    Status,
+    DcTrenner,
+    DcRelais,
+    AcRelais,
+    Wassertemp,
+    WassertempMin,
+    WassertempMax,
+    SolltempSolar,
+    SolltempNetz,
+    GeraeteTemp,
+    IsoMessung,
+    Solarspannung,
+    Dummy5,
+    Solarstrom,
+    Solarleistung,
+    SolarenergieHeute,
+    SolarenergieGesamt,
+    NetzenergieHeute,
+    Dummy6,
+    Dummy7,
+    Dummy8,
+    Dummy9,
+    Dummy10,
+    Dummy11,
+    Dummy12,
+    Seriennummer,
+    Dummy13,
+}
+
+/// The device's `status` field decoded into named states where known.
+///
+/// The vendor does not document this field beyond the handful of values
+/// we've observed in the wild, so anything outside that range is kept as
+/// [`DeviceStatus::Unknown`] rather than guessed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum KnownDeviceStatus {
+    Standby = 0,
+    SolarHeating = 1,
+    GridHeating = 2,
+    SolarAndGridHeating = 3,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub enum DeviceStatus {
+    Known(KnownDeviceStatus),
+    Unknown(u32),
+}
+
+impl std::fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceStatus::Known(known) => write!(f, "{known:?}"),
+            DeviceStatus::Unknown(raw) => write!(f, "Unknown({raw})"),
+        }
+    }
+}
+
+impl From<u32> for DeviceStatus {
+    fn from(raw: u32) -> Self {
+        let known = match raw {
+            0 => KnownDeviceStatus::Standby,
+            1 => KnownDeviceStatus::SolarHeating,
+            2 => KnownDeviceStatus::GridHeating,
+            3 => KnownDeviceStatus::SolarAndGridHeating,
+            _ => return DeviceStatus::Unknown(raw),
+        };
+        DeviceStatus::Known(known)
+    }
+}
+
+/// A single decoded `rs` response from the device.
+///
+/// Unlike the raw TSV line, every field here owns its data so a reading can
+/// be cached beyond the lifetime of the serial read that produced it.
+#[derive(Debug, Clone)]
+pub struct Status {
+    // Wasser
+    pub wassertemp: ThermodynamicTemperature,
+    pub wassertemp_min: ThermodynamicTemperature,
+    pub wassertemp_max: ThermodynamicTemperature,
+    pub solltemp_solar: ThermodynamicTemperature,
+    pub solltemp_netz: ThermodynamicTemperature,
+
+    // Solar aktuell
+    pub solarspannung: ElectricPotential,
+    pub solarstrom: ElectricCurrent,
+    pub solarleistung: Power,
+
+    // Historie
+    pub solarenergie_heute: Energy,
+    pub solarenergie_gesamt: Energy,
+    pub netzenergie_heute: Energy,
+
+    // Zustand
+    pub iso_messung: u32,
+    pub geraetetemp: ThermodynamicTemperature,
+    pub status: DeviceStatus,
+    pub dc_trenner: bool,
+    pub dc_relais: bool,
+    pub ac_relais: bool,
+
+    // Misc
+    pub betriebstag: u32,
+    pub firmware: String,
+    pub seriennummer: String,
+}
+
+fn field<'a>(map: &HashMap<StatusTag, &'a str>, tag: StatusTag) -> Result<&'a str, DeviceError> {
+    map.get(&tag)
+        .copied()
+        .ok_or(DeviceError::MalformedResponse {
+            expected: StatusTag::iter().count(),
+            actual: map.len(),
+        })
+}
+
+fn parse_field<T>(map: &HashMap<StatusTag, &str>, tag: StatusTag) -> Result<T, DeviceError>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    field(map, tag)?
+        .parse()
+        .map_err(|err: T::Err| DeviceError::FieldParse {
+            tag,
+            message: err.to_string(),
+        })
+}
+
+/// Like [`parse_field`], but for the tenths-of-a-degree encoding the device
+/// uses for the water temperature and setpoint fields. The device
+/// temperature (`GeraeteTemp`) is reported in whole degrees and is not
+/// parsed with this.
+fn parse_decidegrees(
+    map: &HashMap<StatusTag, &str>,
+    tag: StatusTag,
+) -> Result<ThermodynamicTemperature, DeviceError> {
+    let tenths: f32 = parse_field(map, tag)?;
+    Ok(ThermodynamicTemperature::new::<degree_celsius>(
+        tenths / 10.0,
+    ))
+}
+
+/// Parses a raw tab-separated `rs` response into a [`Status`].
+pub fn parse_status(data: &[u8]) -> Result<Status, DeviceError> {
+    let data_string = std::str::from_utf8(data).map_err(|_| DeviceError::MalformedResponse {
+        expected: StatusTag::iter().count(),
+        actual: 0,
+    })?;
+
+    let status_map = StatusTag::iter()
+        .zip(data_string.split('\t'))
+        .collect::<HashMap<StatusTag, &str>>();
+
+    Ok(Status {
+        wassertemp: parse_decidegrees(&status_map, StatusTag::Wassertemp)?,
+        wassertemp_min: parse_decidegrees(&status_map, StatusTag::WassertempMin)?,
+        wassertemp_max: parse_decidegrees(&status_map, StatusTag::WassertempMax)?,
+        solltemp_solar: parse_decidegrees(&status_map, StatusTag::SolltempSolar)?,
+        solltemp_netz: parse_decidegrees(&status_map, StatusTag::SolltempNetz)?,
+        solarspannung: ElectricPotential::new::<volt>(parse_field(
+            &status_map,
+            StatusTag::Solarspannung,
+        )?),
+        solarstrom: ElectricCurrent::new::<ampere>(parse_field(
+            &status_map,
+            StatusTag::Solarstrom,
+        )?),
+        solarleistung: Power::new::<watt>(parse_field(&status_map, StatusTag::Solarleistung)?),
+        solarenergie_heute: Energy::new::<watt_hour>(parse_field(
+            &status_map,
+            StatusTag::SolarenergieHeute,
+        )?),
+        solarenergie_gesamt: Energy::new::<watt_hour>(parse_field(
+            &status_map,
+            StatusTag::SolarenergieGesamt,
+        )?),
+        netzenergie_heute: Energy::new::<watt_hour>(parse_field(
+            &status_map,
+            StatusTag::NetzenergieHeute,
+        )?),
+        iso_messung: parse_field(&status_map, StatusTag::IsoMessung)?,
+        geraetetemp: ThermodynamicTemperature::new::<degree_celsius>(parse_field(
+            &status_map,
+            StatusTag::GeraeteTemp,
+        )?),
+        status: DeviceStatus::from(parse_field::<u32>(&status_map, StatusTag::Status)?),
+        dc_trenner: parse_field::<u8>(&status_map, StatusTag::DcTrenner)? != 0,
+        dc_relais: parse_field::<u8>(&status_map, StatusTag::DcRelais)? != 0,
+        ac_relais: parse_field::<u8>(&status_map, StatusTag::AcRelais)? != 0,
+        betriebstag: parse_field(&status_map, StatusTag::Betriebstag)?,
+        firmware: field(&status_map, StatusTag::Firmware)?.to_owned(),
+        seriennummer: field(&status_map, StatusTag::Seriennummer)?.to_owned(),
+    })
+}
+
+/// A write command understood by the device's control path.
+///
+/// Like [`DeviceStatus`], the exact wire syntax isn't documented by the
+/// vendor beyond the read-only `rs` command; these send a two-letter verb
+/// like `rs`, followed by a tab and the value, and encode setpoints the way
+/// the `rs` response reports them (tenths of a degree).
+#[derive(Debug, Clone, Copy)]
+pub enum Command {
+    SetSolltempSolar(ThermodynamicTemperature),
+    SetSolltempNetz(ThermodynamicTemperature),
+    SetAcRelais(bool),
+}
+
+impl Command {
+    fn to_wire(self) -> String {
+        match self {
+            Command::SetSolltempSolar(t) => {
+                format!(
+                    "ss\t{}\r\n",
+                    (t.get::<degree_celsius>() * 10.0).round() as i32
+                )
+            }
+            Command::SetSolltempNetz(t) => {
+                format!(
+                    "sn\t{}\r\n",
+                    (t.get::<degree_celsius>() * 10.0).round() as i32
+                )
+            }
+            Command::SetAcRelais(on) => format!("sa\t{}\r\n", u8::from(on)),
+        }
+    }
+
+    /// Checks that `status`, freshly read back after sending this command,
+    /// reflects the change.
+    fn applied_to(self, status: &Status) -> bool {
+        match self {
+            Command::SetSolltempSolar(t) => {
+                (status.solltemp_solar.get::<degree_celsius>() - t.get::<degree_celsius>()).abs()
+                    < 0.1
+            }
+            Command::SetSolltempNetz(t) => {
+                (status.solltemp_netz.get::<degree_celsius>() - t.get::<degree_celsius>()).abs()
+                    < 0.1
+            }
+            Command::SetAcRelais(on) => status.ac_relais == on,
+        }
+    }
+}
+
+/// Sends a write `command` to the device, then reads back and parses the
+/// resulting status to confirm it actually took effect.
+pub fn write_command(command: Command) -> Result<Status, DeviceError> {
+    let data = exchange(&command.to_wire())?;
+    let status = parse_status(&data)?;
+
+    if !command.applied_to(&status) {
+        return Err(DeviceError::CommandRejected);
+    }
+
+    Ok(status)
+}
+
+pub fn read_device() -> Result<Vec<u8>, DeviceError> {
+    exchange("rs\r\n")
+}
+
+#[cfg(not(feature = "dummy"))]
+fn exchange(command: &str) -> Result<Vec<u8>, DeviceError> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::time::Duration;
+
+    (|| -> anyhow::Result<Vec<u8>> {
+        let mut port = serialport::new("/dev/ttyUSB0", 9600)
+            .timeout(Duration::from_millis(100))
+            .open()?;
+
+        write!(&mut port, "{command}")?;
+
+        let mut reader = BufReader::new(port);
+
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_until(b'\n', &mut data)?;
+
+        Ok(data)
+    })()
+    .map_err(DeviceError::SerialIo)
+}
+
+#[cfg(feature = "dummy")]
+fn exchange(_command: &str) -> Result<Vec<u8>, DeviceError> {
+    use base64::{engine::general_purpose, Engine as _};
+    const SAMPLE_OUTPUT: &str = "ZHIJVjEuMzEJMzUJMTIJMQkxCTEJMjM1CTE3NQkyNDUJNzU5CTY1MAkyNQk5MAkxODkuNQkxOTAuMDMJMS4xNDM1CTIxNy4yOQk3NzgJOTE3MjUJMAktNwk3LjkJNTI1CTM2OAkzNTgJMjQwCTEJMTIwMTAwMjMwMjEwMDAyMwk3NTkJNg0K";
+    general_purpose::STANDARD
+        .decode(SAMPLE_OUTPUT)
+        .map_err(|err| DeviceError::SerialIo(err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+
+    const SAMPLE_OUTPUT: &str = "ZHIJVjEuMzEJMzUJMTIJMQkxCTEJMjM1CTE3NQkyNDUJNzU5CTY1MAkyNQk5MAkxODkuNQkxOTAuMDMJMS4xNDM1CTIxNy4yOQk3NzgJOTE3MjUJMAktNwk3LjkJNTI1CTM2OAkzNTgJMjQwCTEJMTIwMTAwMjMwMjEwMDAyMwk3NTkJNg0K";
+
+    fn sample_status() -> Status {
+        let data = general_purpose::STANDARD.decode(SAMPLE_OUTPUT).unwrap();
+        parse_status(&data).unwrap()
+    }
+
+    #[test]
+    fn to_wire_encodes_setpoints_in_tenths_of_a_degree() {
+        let command =
+            Command::SetSolltempSolar(ThermodynamicTemperature::new::<degree_celsius>(42.5));
+        assert_eq!(command.to_wire(), "ss\t425\r\n");
+
+        let command =
+            Command::SetSolltempNetz(ThermodynamicTemperature::new::<degree_celsius>(55.0));
+        assert_eq!(command.to_wire(), "sn\t550\r\n");
+
+        assert_eq!(Command::SetAcRelais(true).to_wire(), "sa\t1\r\n");
+        assert_eq!(Command::SetAcRelais(false).to_wire(), "sa\t0\r\n");
+    }
+
+    #[test]
+    fn applied_to_matches_only_the_status_it_was_meant_to_produce() {
+        let status = sample_status();
+
+        assert!(Command::SetSolltempSolar(status.solltemp_solar).applied_to(&status));
+        assert!(!Command::SetSolltempSolar(
+            status.solltemp_solar + ThermodynamicTemperature::new::<degree_celsius>(5.0)
+        )
+        .applied_to(&status));
+
+        assert!(Command::SetAcRelais(status.ac_relais).applied_to(&status));
+        assert!(!Command::SetAcRelais(!status.ac_relais).applied_to(&status));
+    }
+}
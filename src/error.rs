@@ -0,0 +1,104 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::device::StatusTag;
+
+/// Everything that can go wrong while talking to the device or decoding its
+/// response, broken out so API consumers get a machine-readable category
+/// instead of an opaque 500.
+#[derive(Debug, Error)]
+pub enum DeviceError {
+    #[error("could not communicate with the serial device")]
+    SerialIo(#[source] anyhow::Error),
+
+    #[error("device response had {actual} fields, expected at least {expected}")]
+    MalformedResponse { expected: usize, actual: usize },
+
+    #[error("could not parse field {tag:?}: {message}")]
+    FieldParse { tag: StatusTag, message: String },
+
+    #[error("device did not apply the requested command")]
+    CommandRejected,
+}
+
+impl DeviceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DeviceError::SerialIo(_) => StatusCode::SERVICE_UNAVAILABLE,
+            DeviceError::MalformedResponse { .. } | DeviceError::FieldParse { .. } => {
+                StatusCode::BAD_GATEWAY
+            }
+            DeviceError::CommandRejected => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            DeviceError::SerialIo(_) => "serial_io_error",
+            DeviceError::MalformedResponse { .. } => "malformed_response",
+            DeviceError::FieldParse { .. } => "field_parse_error",
+            DeviceError::CommandRejected => "command_rejected",
+        }
+    }
+}
+
+/// Top-level error type for request handlers.
+///
+/// Wraps [`DeviceError`] for anything that happened talking to the device,
+/// plus a `NoReading` variant for the (brief, startup-only) window before
+/// the background poller has produced a first reading.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+
+    #[error("no successful device reading is available yet")]
+    NoReading,
+
+    #[error("electricity price data is not configured or not available yet")]
+    PriceUnavailable,
+
+    #[error("the control API is not configured")]
+    ControlDisabled,
+
+    #[error("missing or invalid control API token")]
+    Unauthorized,
+
+    #[error("internal error")]
+    Internal(#[from] anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            AppError::Device(err) => (err.status_code(), err.code()),
+            AppError::NoReading => (StatusCode::SERVICE_UNAVAILABLE, "no_reading"),
+            AppError::PriceUnavailable => (StatusCode::SERVICE_UNAVAILABLE, "price_unavailable"),
+            AppError::ControlDisabled => (StatusCode::NOT_FOUND, "control_disabled"),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        };
+
+        log::warn!("Request failed: {self:#}");
+
+        (
+            status,
+            Json(ErrorBody {
+                code,
+                message: self.to_string(),
+            }),
+        )
+            .into_response()
+    }
+}
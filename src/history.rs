@@ -0,0 +1,226 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use axum::{extract::State, Json};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use uom::si::{
+    electric_current::ampere, electric_potential::volt, energy::watt_hour, power::watt,
+    thermodynamic_temperature::degree_celsius,
+};
+
+use crate::device::Status;
+use crate::error::AppError;
+use crate::AppState;
+
+/// How finely readings get bucketed before being persisted. Several polls
+/// landing in the same bucket overwrite each other, so the database grows
+/// at one row per bucket (holding every metric) rather than one row per poll.
+const DOWNSAMPLE_INTERVAL_SECS: i64 = 60;
+
+/// How long history is kept before being pruned, so a device running for
+/// years doesn't grow the database without bound.
+const RETENTION_DAYS: i64 = 2 * 365;
+
+pub type SharedDb = Arc<Mutex<Connection>>;
+
+/// Rounds `timestamp` down to the start of the [`DOWNSAMPLE_INTERVAL_SECS`]
+/// bucket that contains it.
+fn downsample_bucket(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(DOWNSAMPLE_INTERVAL_SECS)
+}
+
+/// Opens (creating if necessary) the history database at `path`.
+pub fn open(path: &Path) -> anyhow::Result<SharedDb> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("Could not open history database at {}", path.display()))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS readings (
+            bucket INTEGER PRIMARY KEY,
+            wassertemp_celsius REAL NOT NULL,
+            wassertemp_min_celsius REAL NOT NULL,
+            wassertemp_max_celsius REAL NOT NULL,
+            solltemp_solar_celsius REAL NOT NULL,
+            solltemp_netz_celsius REAL NOT NULL,
+            solarspannung_volt REAL NOT NULL,
+            solarstrom_ampere REAL NOT NULL,
+            solarleistung_watt REAL NOT NULL,
+            geraetetemp_celsius REAL NOT NULL,
+            solarenergie_heute_watt_hour REAL NOT NULL,
+            solarenergie_gesamt_watt_hour REAL NOT NULL,
+            netzenergie_heute_watt_hour REAL NOT NULL
+        )",
+    )
+    .context("Could not initialize history schema")?;
+
+    Ok(Arc::new(Mutex::new(conn)))
+}
+
+/// Records one reading, downsampling into the bucket covering `timestamp`
+/// and pruning anything older than [`RETENTION_DAYS`].
+pub fn record(db: &SharedDb, timestamp: i64, status: &Status) -> anyhow::Result<()> {
+    let bucket = downsample_bucket(timestamp);
+
+    let conn = db.lock().unwrap();
+
+    conn.execute(
+        "INSERT INTO readings (
+            bucket, wassertemp_celsius, wassertemp_min_celsius, wassertemp_max_celsius,
+            solltemp_solar_celsius, solltemp_netz_celsius,
+            solarspannung_volt, solarstrom_ampere, solarleistung_watt, geraetetemp_celsius,
+            solarenergie_heute_watt_hour, solarenergie_gesamt_watt_hour, netzenergie_heute_watt_hour
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+        ON CONFLICT(bucket) DO UPDATE SET
+            wassertemp_celsius = excluded.wassertemp_celsius,
+            wassertemp_min_celsius = excluded.wassertemp_min_celsius,
+            wassertemp_max_celsius = excluded.wassertemp_max_celsius,
+            solltemp_solar_celsius = excluded.solltemp_solar_celsius,
+            solltemp_netz_celsius = excluded.solltemp_netz_celsius,
+            solarspannung_volt = excluded.solarspannung_volt,
+            solarstrom_ampere = excluded.solarstrom_ampere,
+            solarleistung_watt = excluded.solarleistung_watt,
+            geraetetemp_celsius = excluded.geraetetemp_celsius,
+            solarenergie_heute_watt_hour = excluded.solarenergie_heute_watt_hour,
+            solarenergie_gesamt_watt_hour = excluded.solarenergie_gesamt_watt_hour,
+            netzenergie_heute_watt_hour = excluded.netzenergie_heute_watt_hour",
+        params![
+            bucket,
+            status.wassertemp.get::<degree_celsius>(),
+            status.wassertemp_min.get::<degree_celsius>(),
+            status.wassertemp_max.get::<degree_celsius>(),
+            status.solltemp_solar.get::<degree_celsius>(),
+            status.solltemp_netz.get::<degree_celsius>(),
+            status.solarspannung.get::<volt>(),
+            status.solarstrom.get::<ampere>(),
+            status.solarleistung.get::<watt>(),
+            status.geraetetemp.get::<degree_celsius>(),
+            status.solarenergie_heute.get::<watt_hour>(),
+            status.solarenergie_gesamt.get::<watt_hour>(),
+            status.netzenergie_heute.get::<watt_hour>(),
+        ],
+    )
+    .context("Could not insert reading into history database")?;
+
+    let retention_cutoff = timestamp - RETENTION_DAYS * 24 * 3600;
+    conn.execute(
+        "DELETE FROM readings WHERE bucket < ?1",
+        params![retention_cutoff],
+    )
+    .context("Could not prune old history rows")?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct SeriesPoint {
+    pub timestamp: i64,
+    pub value: f32,
+}
+
+/// `solarleistung`, one point per bucket, for the last 24 hours.
+pub fn solarleistung_last_24h(db: &SharedDb, now: i64) -> anyhow::Result<Vec<SeriesPoint>> {
+    series_since(db, "solarleistung_watt", now - 24 * 3600)
+}
+
+/// Today's `solarenergie_heute` curve, from local midnight to now. The
+/// device's own `*_heute` counters reset at local midnight, so the query
+/// boundary has to track the server's local offset rather than UTC midnight.
+pub fn solarenergie_heute_curve(db: &SharedDb, now: i64) -> anyhow::Result<Vec<SeriesPoint>> {
+    series_since(db, "solarenergie_heute_watt_hour", local_midnight(now))
+}
+
+/// Today's `netzenergie_heute` curve, from local midnight to now. Used to
+/// price grid heating against the spot price active when each sample was
+/// taken.
+pub fn netzenergie_heute_today(db: &SharedDb, now: i64) -> anyhow::Result<Vec<SeriesPoint>> {
+    series_since(db, "netzenergie_heute_watt_hour", local_midnight(now))
+}
+
+/// The Unix timestamp of local midnight for the day containing `now`, in the
+/// server's local timezone (falls back to the UTC day boundary if the local
+/// offset can't be resolved for some reason).
+fn local_midnight(now: i64) -> i64 {
+    use chrono::{Local, TimeZone};
+
+    Local
+        .timestamp_opt(now, 0)
+        .single()
+        .and_then(|dt| dt.date_naive().and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).single())
+        .map(|midnight| midnight.timestamp())
+        .unwrap_or_else(|| now - now.rem_euclid(24 * 3600))
+}
+
+fn series_since(db: &SharedDb, column: &str, since: i64) -> anyhow::Result<Vec<SeriesPoint>> {
+    let conn = db.lock().unwrap();
+
+    let mut statement = conn
+        .prepare(&format!(
+            "SELECT bucket, {column} FROM readings WHERE bucket >= ?1 ORDER BY bucket ASC"
+        ))
+        .context("Could not prepare history query")?;
+
+    let rows = statement
+        .query_map(params![since], |row| {
+            Ok(SeriesPoint {
+                timestamp: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })
+        .context("Could not query history")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Could not read history row")?;
+
+    Ok(rows)
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    solarleistung_last_24h: Vec<SeriesPoint>,
+    solarenergie_heute: Vec<SeriesPoint>,
+}
+
+pub async fn history_handler(
+    State(state): State<AppState>,
+) -> Result<Json<HistoryResponse>, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let db = state.db.clone();
+    tokio::task::spawn_blocking(move || {
+        Ok(Json(HistoryResponse {
+            solarleistung_last_24h: solarleistung_last_24h(&db, now)?,
+            solarenergie_heute: solarenergie_heute_curve(&db, now)?,
+        }))
+    })
+    .await
+    .context("History query task panicked")?
+    .map_err(AppError::Internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_bucket_rounds_down_to_the_interval() {
+        assert_eq!(downsample_bucket(0), 0);
+        assert_eq!(downsample_bucket(59), 0);
+        assert_eq!(downsample_bucket(60), 60);
+        assert_eq!(downsample_bucket(119), 60);
+        assert_eq!(downsample_bucket(120), 120);
+    }
+
+    #[test]
+    fn local_midnight_is_within_a_day_before_now() {
+        let now = 1_700_000_000;
+        let midnight = local_midnight(now);
+        assert!(midnight <= now);
+        assert!(now - midnight < 24 * 3600);
+    }
+}
@@ -1,13 +1,23 @@
-use std::collections::HashMap;
+mod api;
+mod control;
+mod device;
+mod error;
+mod history;
+mod metrics;
+mod mqtt;
+mod poller;
+mod price;
+
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use anyhow::Context;
 use axum::{
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::get,
+    extract::State,
+    response::Html,
+    routing::{get, post},
     Router,
 };
-use strum::{EnumIter, IntoEnumIterator};
+use tokio::sync::RwLock;
 use uom::{
     fmt::DisplayStyle::Abbreviation,
     si::{
@@ -20,156 +30,96 @@ use uom::{
     },
 };
 
-#[derive(EnumIter, PartialEq, Eq, Hash, Debug)]
-enum StatusTag {
-    Dummy0,
-    Firmware,
-    Betriebstag,
-    Status,
-    DcTrenner,
-    DcRelais,
-    AcRelais,
-    Wassertemp,
-    WassertempMin,
-    WassertempMax,
-    SolltempSolar,
-    SolltempNetz,
-    GeraeteTemp,
-    IsoMessung,
-    Solarspannung,
-    Dummy5,
-    Solarstrom,
-    Solarleistung,
-    SolarenergieHeute,
-    SolarenergieGesamt,
-    NetzenergieHeute,
-    Dummy6,
-    Dummy7,
-    Dummy8,
-    Dummy9,
-    Dummy10,
-    Dummy11,
-    Dummy12,
-    Seriennummer,
-    Dummy13,
-}
+use error::AppError;
+use history::SharedDb;
+use poller::SharedReading;
+use price::SharedPrices;
 
-#[derive(Debug)]
-struct Status<'a> {
-    // Wasser
-    wassertemp: ThermodynamicTemperature,
-    wassertemp_min: ThermodynamicTemperature,
-    wassertemp_max: ThermodynamicTemperature,
-    solltemp_solar: ThermodynamicTemperature,
-    solltemp_netz: ThermodynamicTemperature,
-
-    // Solar aktuell
-    solarspannung: ElectricPotential,
-    solarstrom: ElectricCurrent,
-    solarleistung: Power,
-
-    // Historie
-    solarenergie_heute: Energy,
-    solarenergie_gesamt: Energy,
-    netzenergie_heute: Energy,
-
-    // Zustand
-    iso_messung: u32,
-    geraetetemp: ThermodynamicTemperature,
-    status: u32,
-    dc_trenner: bool,
-    dc_relais: bool,
-    ac_relais: bool,
-
-    // Misc
-    betriebstag: u32,
-    firmware: &'a str,
-    seriennummer: &'a str,
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) reading: SharedReading,
+    pub(crate) db: SharedDb,
+    pub(crate) prices: SharedPrices,
 }
 
-struct AppError(anyhow::Error);
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let reading: SharedReading = Arc::new(RwLock::new(None));
+
+    let db_path = std::env::var("ELWA_DB_PATH").unwrap_or_else(|_| "elwa-dc.sqlite3".to_owned());
+    let db = history::open(&PathBuf::from(db_path))?;
+
+    let (mqtt_publisher, mqtt_commands) = match mqtt::MqttConfig::from_env() {
+        Ok(Some(config)) => match mqtt::MqttPublisher::connect(&config).await {
+            Ok((publisher, commands)) => (Some(publisher), Some(commands)),
+            Err(err) => {
+                log::error!("Could not connect to MQTT broker, publishing disabled: {err:#}");
+                (None, None)
+            }
+        },
+        Ok(None) => (None, None),
+        Err(err) => {
+            log::error!("Invalid MQTT configuration, publishing disabled: {err:#}");
+            (None, None)
+        }
+    };
+
+    tokio::spawn(poller::run(reading.clone(), db.clone(), mqtt_publisher));
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Internal Server Error:\n{:?}", self.0),
-        )
-            .into_response()
+    let prices: SharedPrices = Arc::new(RwLock::new(Vec::new()));
+    if let Some(price_config) = price::PriceConfig::from_env() {
+        tokio::spawn(price::run(prices.clone(), price_config));
     }
-}
 
-impl<E> From<E> for AppError
-where
-    E: Into<anyhow::Error>,
-{
-    fn from(err: E) -> Self {
-        Self(err.into())
+    let state = AppState {
+        reading,
+        db,
+        prices,
+    };
+
+    if let Some(mut mqtt_commands) = mqtt_commands {
+        if std::env::var("ELWA_CONTROL_TOKEN").is_ok() {
+            let state = state.clone();
+            tokio::spawn(async move {
+                while let Some(command) = mqtt_commands.recv().await {
+                    if let Err(err) = control::apply_command(&state, command).await {
+                        log::warn!("Could not apply MQTT command: {err:#}");
+                    }
+                }
+            });
+        } else {
+            log::warn!(
+                "ELWA_CONTROL_TOKEN is not set, control is disabled: ignoring MQTT command topics"
+            );
+        }
     }
-}
 
-#[tokio::main]
-async fn main() {
-    let app = Router::new().route("/", get(handler));
+    let app = Router::new()
+        .route("/", get(handler))
+        .route("/api/status", get(api::status_handler))
+        .route("/api/price", get(api::price_handler))
+        .route("/history", get(history::history_handler))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/control/solltemp_solar", post(control::set_solltemp_solar))
+        .route("/control/solltemp_netz", post(control::set_solltemp_netz))
+        .route("/control/ac_relais", post(control::set_ac_relais))
+        .with_state(state);
 
     axum::Server::bind(&"0.0.0.0:3000".parse().unwrap())
         .serve(app.into_make_service())
-        .await
-        .unwrap();
+        .await?;
+
+    Ok(())
 }
 
-async fn handler() -> Result<Html<String>, AppError> {
-    log::info!("Fetch new data");
-
-    let data = read_device().context("Could not retrieve device data")?;
-    let data_string = std::str::from_utf8(&data).unwrap();
-
-    let status_map = StatusTag::iter()
-        .zip(data_string.split('\t'))
-        .collect::<HashMap<StatusTag, &str>>();
-
-    let status = Status {
-        wassertemp: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::Wassertemp].parse::<f32>()? / 10.0,
-        ),
-        wassertemp_min: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::WassertempMin].parse::<f32>()? / 10.0,
-        ),
-        wassertemp_max: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::WassertempMax].parse::<f32>()? / 10.0,
-        ),
-        solltemp_solar: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::SolltempSolar].parse::<f32>()? / 10.0,
-        ),
-        solltemp_netz: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::SolltempNetz].parse::<f32>()? / 10.0,
-        ),
-        solarspannung: ElectricPotential::new::<volt>(
-            status_map[&StatusTag::Solarspannung].parse()?,
-        ),
-        solarstrom: ElectricCurrent::new::<ampere>(status_map[&StatusTag::Solarstrom].parse()?),
-        solarleistung: Power::new::<watt>(status_map[&StatusTag::Solarleistung].parse()?),
-        solarenergie_heute: Energy::new::<watt_hour>(
-            status_map[&StatusTag::SolarenergieHeute].parse()?,
-        ),
-        solarenergie_gesamt: Energy::new::<watt_hour>(
-            status_map[&StatusTag::SolarenergieGesamt].parse()?,
-        ),
-        netzenergie_heute: Energy::new::<watt_hour>(
-            status_map[&StatusTag::NetzenergieHeute].parse()?,
-        ),
-        iso_messung: status_map[&StatusTag::IsoMessung].parse()?,
-        geraetetemp: ThermodynamicTemperature::new::<degree_celsius>(
-            status_map[&StatusTag::GeraeteTemp].parse()?,
-        ),
-        status: status_map[&StatusTag::Status].parse()?,
-        dc_trenner: status_map[&StatusTag::DcTrenner].parse::<u8>()? != 0,
-        dc_relais: status_map[&StatusTag::DcRelais].parse::<u8>()? != 0,
-        ac_relais: status_map[&StatusTag::AcRelais].parse::<u8>()? != 0,
-        betriebstag: status_map[&StatusTag::Betriebstag].parse()?,
-        firmware: status_map[&StatusTag::Firmware],
-        seriennummer: status_map[&StatusTag::Seriennummer],
-    };
+async fn handler(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    let reading = state.reading.read().await;
+    let reading = reading.as_ref().ok_or(AppError::NoReading)?;
+
+    let status = &reading.status;
+    let age_secs = reading.age().as_secs();
 
     let w = Power::format_args(watt, Abbreviation);
     let kw = Power::format_args(kilowatt, Abbreviation);
@@ -179,6 +129,34 @@ async fn handler() -> Result<Html<String>, AppError> {
     let a = ElectricCurrent::format_args(ampere, Abbreviation);
     let c = ThermodynamicTemperature::format_args(degree_celsius, Abbreviation);
 
+    let price_dto = api::compute_price_dto(
+        &state,
+        status.wassertemp.get::<degree_celsius>(),
+        status.solltemp_netz.get::<degree_celsius>(),
+    )
+    .await
+    .ok();
+
+    let format_price = |p: Option<f64>| p.map_or_else(|| "n/a".to_owned(), |p| format!("{p:.4}"));
+    let grid_heating_cost_today = format_price(
+        price_dto
+            .as_ref()
+            .map(|dto| dto.grid_heating_cost_today_currency),
+    );
+    let current_price = format_price(
+        price_dto
+            .as_ref()
+            .and_then(|dto| dto.recommendation.current_price),
+    );
+    let cheapest_upcoming_price = format_price(
+        price_dto
+            .as_ref()
+            .and_then(|dto| dto.recommendation.cheapest_upcoming_price),
+    );
+    let grid_heating_favorable_now = price_dto
+        .as_ref()
+        .is_some_and(|dto| dto.recommendation.grid_heating_favorable_now);
+
     Ok(Html(format!(
         include_str!("index.html"),
         c.with(status.wassertemp),
@@ -203,36 +181,12 @@ async fn handler() -> Result<Html<String>, AppError> {
         status.dc_relais,
         status.ac_relais,
         status.betriebstag,
-        status.firmware,
-        status.seriennummer,
+        &status.firmware,
+        &status.seriennummer,
+        age_secs,
+        current_price,
+        cheapest_upcoming_price,
+        grid_heating_favorable_now,
+        grid_heating_cost_today,
     )))
 }
-
-#[cfg(not(feature = "dummy"))]
-fn read_device() -> anyhow::Result<Vec<u8>> {
-    use std::io::{BufRead, BufReader};
-    use std::time::Duration;
-
-    let mut port = serialport::new("/dev/ttyUSB0", 9600)
-        .timeout(Duration::from_millis(100))
-        .open()
-        .context("Could not open serial device port")?;
-
-    write!(&mut port, "rs\r\n").context("Could not write to serial connection")?;
-
-    let mut reader = BufReader::new(port);
-
-    let mut data: Vec<u8> = Vec::new();
-    reader
-        .read_until(b'\n', &mut data)
-        .context("Could not read from serial connection")?;
-
-    Ok(data)
-}
-
-#[cfg(feature = "dummy")]
-fn read_device() -> anyhow::Result<Vec<u8>> {
-    use base64::{engine::general_purpose, Engine as _};
-    const SAMPLE_OUTPUT: &str = "ZHIJVjEuMzEJMzUJMTIJMQkxCTEJMjM1CTE3NQkyNDUJNzU5CTY1MAkyNQk5MAkxODkuNQkxOTAuMDMJMS4xNDM1CTIxNy4yOQk3NzgJOTE3MjUJMAktNwk3LjkJNTI1CTM2OAkzNTgJMjQwCTEJMTIwMTAwMjMwMjEwMDAyMwk3NTkJNg0K";
-    Ok(general_purpose::STANDARD.decode(SAMPLE_OUTPUT)?)
-}
@@ -0,0 +1,154 @@
+use std::fmt::Write as _;
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use uom::si::{
+    electric_current::ampere, electric_potential::volt, energy::watt_hour, power::watt,
+    thermodynamic_temperature::degree_celsius,
+};
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// Serves the current cached reading as Prometheus text-format gauges.
+///
+/// Every value is converted to one fixed unit per quantity (°C, V, A, W, Wh;
+/// the same conversion `StatusDto` uses for the JSON API), relay/boolean fields are
+/// emitted as 0/1 gauges, and firmware/serial are attached as labels on an
+/// info metric rather than their own gauges.
+pub async fn metrics_handler(State(state): State<AppState>) -> Result<Response, AppError> {
+    let reading = state.reading.read().await;
+    let reading = reading.as_ref().ok_or(AppError::NoReading)?;
+    let status = &reading.status;
+
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "elwa_device_info",
+        "Static device identification",
+        &format!(
+            "elwa_device_info{{firmware=\"{}\",serial=\"{}\"}}",
+            status.firmware, status.seriennummer
+        ),
+        1.0,
+    );
+
+    gauge(
+        &mut out,
+        "elwa_water_temp_celsius",
+        "Current water temperature",
+        "elwa_water_temp_celsius",
+        status.wassertemp.get::<degree_celsius>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_water_temp_min_celsius",
+        "Minimum configured water temperature",
+        "elwa_water_temp_min_celsius",
+        status.wassertemp_min.get::<degree_celsius>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_water_temp_max_celsius",
+        "Maximum configured water temperature",
+        "elwa_water_temp_max_celsius",
+        status.wassertemp_max.get::<degree_celsius>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_device_temp_celsius",
+        "Controller device temperature",
+        "elwa_device_temp_celsius",
+        status.geraetetemp.get::<degree_celsius>(),
+    );
+
+    gauge(
+        &mut out,
+        "elwa_solar_voltage_volts",
+        "Solar input voltage",
+        "elwa_solar_voltage_volts",
+        status.solarspannung.get::<volt>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_solar_current_amperes",
+        "Solar input current",
+        "elwa_solar_current_amperes",
+        status.solarstrom.get::<ampere>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_solar_power_watts",
+        "Current solar power",
+        "elwa_solar_power_watts",
+        status.solarleistung.get::<watt>(),
+    );
+
+    gauge(
+        &mut out,
+        "elwa_solar_energy_today_watt_hours",
+        "Solar energy harvested today",
+        "elwa_solar_energy_today_watt_hours",
+        status.solarenergie_heute.get::<watt_hour>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_solar_energy_total_watt_hours",
+        "Solar energy harvested since installation",
+        "elwa_solar_energy_total_watt_hours",
+        status.solarenergie_gesamt.get::<watt_hour>(),
+    );
+    gauge(
+        &mut out,
+        "elwa_grid_energy_today_watt_hours",
+        "Grid energy used for backup heating today",
+        "elwa_grid_energy_today_watt_hours",
+        status.netzenergie_heute.get::<watt_hour>(),
+    );
+
+    gauge(
+        &mut out,
+        "elwa_isolation_measurement",
+        "Isolation measurement reading",
+        "elwa_isolation_measurement",
+        status.iso_messung as f64,
+    );
+
+    gauge(
+        &mut out,
+        "elwa_dc_trenner",
+        "DC disconnect relay state (1 = closed)",
+        "elwa_dc_trenner",
+        status.dc_trenner as u8 as f64,
+    );
+    gauge(
+        &mut out,
+        "elwa_dc_relais",
+        "DC relay state (1 = on)",
+        "elwa_dc_relais",
+        status.dc_relais as u8 as f64,
+    );
+    gauge(
+        &mut out,
+        "elwa_ac_relais",
+        "AC (grid) relay state (1 = on)",
+        "elwa_ac_relais",
+        status.ac_relais as u8 as f64,
+    );
+
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response())
+}
+
+/// Appends one gauge's `# HELP`/`# TYPE` header and sample line to `out`.
+/// `name` is the metric name used in the header comments, `sample` is the
+/// full sample (name plus any `{labels}`).
+fn gauge(out: &mut String, name: &str, help: &str, sample: &str, value: impl Into<f64>) {
+    let value: f64 = value.into();
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{sample} {value}");
+}
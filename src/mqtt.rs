@@ -0,0 +1,405 @@
+use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use uom::si::{
+    energy::watt_hour, f32::ThermodynamicTemperature, power::watt,
+    thermodynamic_temperature::degree_celsius,
+};
+
+use crate::device::{Command, Status};
+
+/// Where readings get published on the broker.
+///
+/// Readings are published on every successful poll; there is no separate
+/// publish interval.
+///
+/// `None` (the default) means the MQTT subsystem is disabled entirely; the
+/// poller then never touches the network.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub broker_url: String,
+    pub base_topic: String,
+}
+
+impl MqttConfig {
+    /// Builds a config from the environment, returning `None` if MQTT is not
+    /// configured (i.e. `ELWA_MQTT_BROKER_URL` is unset).
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(broker_url) = std::env::var("ELWA_MQTT_BROKER_URL") else {
+            return Ok(None);
+        };
+
+        let base_topic =
+            std::env::var("ELWA_MQTT_BASE_TOPIC").unwrap_or_else(|_| "elwa-dc".to_owned());
+
+        Ok(Some(Self {
+            broker_url,
+            base_topic,
+        }))
+    }
+}
+
+/// A connected publisher that mirrors device readings onto an MQTT broker,
+/// with Home Assistant MQTT discovery so entities show up automatically.
+pub struct MqttPublisher {
+    client: AsyncClient,
+    base_topic: String,
+    discovery_published: bool,
+}
+
+impl MqttPublisher {
+    /// Connects to the broker and subscribes to `{base_topic}/set/+` so
+    /// setpoints and the grid relay can be controlled via MQTT as well as the
+    /// HTTP control routes.
+    ///
+    /// Returns the publisher alongside a receiver that yields a
+    /// [`Command`] for every recognized, well-formed message published to one
+    /// of those command topics; the caller is responsible for actually
+    /// applying them (see [`crate::control::apply_command`]).
+    pub async fn connect(
+        config: &MqttConfig,
+    ) -> anyhow::Result<(Self, mpsc::UnboundedReceiver<Command>)> {
+        let mut options = MqttOptions::parse_url(&config.broker_url)?;
+        options.set_keep_alive(std::time::Duration::from_secs(30));
+
+        let (client, mut eventloop) = AsyncClient::new(options, 16);
+
+        client
+            .subscribe(format!("{}/set/+", config.base_topic), QoS::AtLeastOnce)
+            .await?;
+
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let base_topic = config.base_topic.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                        if let Some(command) =
+                            parse_command(&base_topic, &publish.topic, &publish.payload)
+                        {
+                            let _ = command_tx.send(command);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::warn!("MQTT connection error: {err:#}"),
+                }
+            }
+        });
+
+        Ok((
+            Self {
+                client,
+                base_topic: config.base_topic.clone(),
+                discovery_published: false,
+            },
+            command_rx,
+        ))
+    }
+
+    /// Publishes the current reading as individual, retained topics, and
+    /// (once) the Home Assistant discovery configs that describe them.
+    pub async fn publish(&mut self, status: &Status) -> anyhow::Result<()> {
+        if !self.discovery_published {
+            self.publish_discovery().await?;
+            self.discovery_published = true;
+        }
+
+        self.publish_numeric("wassertemp", status.wassertemp.get::<degree_celsius>())
+            .await?;
+        self.publish_numeric(
+            "wassertemp_min",
+            status.wassertemp_min.get::<degree_celsius>(),
+        )
+        .await?;
+        self.publish_numeric(
+            "wassertemp_max",
+            status.wassertemp_max.get::<degree_celsius>(),
+        )
+        .await?;
+        self.publish_numeric(
+            "solltemp_solar",
+            status.solltemp_solar.get::<degree_celsius>(),
+        )
+        .await?;
+        self.publish_numeric(
+            "solltemp_netz",
+            status.solltemp_netz.get::<degree_celsius>(),
+        )
+        .await?;
+        self.publish_numeric("solarleistung", status.solarleistung.get::<watt>())
+            .await?;
+        self.publish_numeric(
+            "solarenergie_heute",
+            status.solarenergie_heute.get::<watt_hour>(),
+        )
+        .await?;
+        self.publish_numeric(
+            "solarenergie_gesamt",
+            status.solarenergie_gesamt.get::<watt_hour>(),
+        )
+        .await?;
+        self.publish_numeric(
+            "netzenergie_heute",
+            status.netzenergie_heute.get::<watt_hour>(),
+        )
+        .await?;
+        self.publish_relay("dc_relais", status.dc_relais).await?;
+        self.publish_relay("ac_relais", status.ac_relais).await?;
+
+        Ok(())
+    }
+
+    async fn publish_numeric(&self, field: &str, value: f32) -> anyhow::Result<()> {
+        self.client
+            .publish(
+                format!("{}/{field}", self.base_topic),
+                QoS::AtLeastOnce,
+                true,
+                value.to_string(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn publish_relay(&self, field: &str, on: bool) -> anyhow::Result<()> {
+        self.client
+            .publish(
+                format!("{}/{field}", self.base_topic),
+                QoS::AtLeastOnce,
+                true,
+                if on { "ON" } else { "OFF" },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn publish_discovery(&self) -> anyhow::Result<()> {
+        for sensor in DISCOVERY_SENSORS {
+            let config = HomeAssistantSensorConfig {
+                name: sensor.name,
+                state_topic: format!("{}/{}", self.base_topic, sensor.field),
+                unit_of_measurement: sensor.unit,
+                device_class: sensor.device_class,
+                unique_id: format!("elwa_dc_{}", sensor.field),
+            };
+
+            self.publish_discovery_config("sensor", sensor.field, &config)
+                .await?;
+        }
+
+        for binary_sensor in DISCOVERY_BINARY_SENSORS {
+            let config = HomeAssistantBinarySensorConfig {
+                name: binary_sensor.name,
+                state_topic: format!("{}/{}", self.base_topic, binary_sensor.field),
+                device_class: binary_sensor.device_class,
+                unique_id: format!("elwa_dc_{}", binary_sensor.field),
+            };
+
+            self.publish_discovery_config("binary_sensor", binary_sensor.field, &config)
+                .await?;
+        }
+
+        for switch in DISCOVERY_SWITCHES {
+            let config = HomeAssistantSwitchConfig {
+                name: switch.name,
+                state_topic: format!("{}/{}", self.base_topic, switch.field),
+                command_topic: format!("{}/set/{}", self.base_topic, switch.field),
+                unique_id: format!("elwa_dc_{}", switch.field),
+            };
+
+            self.publish_discovery_config("switch", switch.field, &config)
+                .await?;
+        }
+
+        for number in DISCOVERY_NUMBERS {
+            let config = HomeAssistantNumberConfig {
+                name: number.name,
+                state_topic: format!("{}/{}", self.base_topic, number.field),
+                command_topic: format!("{}/set/{}", self.base_topic, number.field),
+                unit_of_measurement: number.unit,
+                min: number.min,
+                max: number.max,
+                step: number.step,
+                unique_id: format!("elwa_dc_{}", number.field),
+            };
+
+            self.publish_discovery_config("number", number.field, &config)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn publish_discovery_config(
+        &self,
+        component: &str,
+        field: &str,
+        config: &impl Serialize,
+    ) -> anyhow::Result<()> {
+        let topic = format!("homeassistant/{component}/elwa_dc/{field}/config");
+        self.client
+            .publish(topic, QoS::AtLeastOnce, true, serde_json::to_vec(config)?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Parses an incoming publish on one of the `{base_topic}/set/+` command
+/// topics into a [`Command`], using the same payload conventions `publish`
+/// uses on the way out (a bare number for setpoints, `ON`/`OFF` for relays).
+/// Returns `None` for anything on an unrecognized topic or with a payload we
+/// can't parse, rather than erroring the whole eventloop task over a bad
+/// message.
+fn parse_command(base_topic: &str, topic: &str, payload: &[u8]) -> Option<Command> {
+    let field = topic.strip_prefix(&format!("{base_topic}/set/"))?;
+    let payload = std::str::from_utf8(payload).ok()?.trim();
+
+    match field {
+        "solltemp_solar" => payload.parse().ok().map(|celsius| {
+            Command::SetSolltempSolar(ThermodynamicTemperature::new::<degree_celsius>(celsius))
+        }),
+        "solltemp_netz" => payload.parse().ok().map(|celsius| {
+            Command::SetSolltempNetz(ThermodynamicTemperature::new::<degree_celsius>(celsius))
+        }),
+        "ac_relais" => match payload {
+            "ON" => Some(Command::SetAcRelais(true)),
+            "OFF" => Some(Command::SetAcRelais(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct DiscoverySensor {
+    field: &'static str,
+    name: &'static str,
+    unit: &'static str,
+    device_class: &'static str,
+}
+
+const DISCOVERY_SENSORS: &[DiscoverySensor] = &[
+    DiscoverySensor {
+        field: "wassertemp",
+        name: "ELWA Wassertemperatur",
+        unit: "°C",
+        device_class: "temperature",
+    },
+    DiscoverySensor {
+        field: "solarleistung",
+        name: "ELWA Solarleistung",
+        unit: "W",
+        device_class: "power",
+    },
+    DiscoverySensor {
+        field: "solarenergie_heute",
+        name: "ELWA Solarenergie heute",
+        unit: "Wh",
+        device_class: "energy",
+    },
+    DiscoverySensor {
+        field: "solarenergie_gesamt",
+        name: "ELWA Solarenergie gesamt",
+        unit: "Wh",
+        device_class: "energy",
+    },
+    DiscoverySensor {
+        field: "netzenergie_heute",
+        name: "ELWA Netzenergie heute",
+        unit: "Wh",
+        device_class: "energy",
+    },
+];
+
+#[derive(Serialize)]
+struct HomeAssistantSensorConfig {
+    name: &'static str,
+    state_topic: String,
+    unit_of_measurement: &'static str,
+    device_class: &'static str,
+    unique_id: String,
+}
+
+/// A relay whose state is reported but can't be written (no matching
+/// [`Command`] variant), surfaced to Home Assistant as a `binary_sensor`.
+struct DiscoveryBinarySensor {
+    field: &'static str,
+    name: &'static str,
+    device_class: &'static str,
+}
+
+const DISCOVERY_BINARY_SENSORS: &[DiscoveryBinarySensor] = &[DiscoveryBinarySensor {
+    field: "dc_relais",
+    name: "ELWA DC-Relais",
+    device_class: "power",
+}];
+
+#[derive(Serialize)]
+struct HomeAssistantBinarySensorConfig {
+    name: &'static str,
+    state_topic: String,
+    device_class: &'static str,
+    unique_id: String,
+}
+
+/// A relay that's both reported and settable, surfaced to Home Assistant as a
+/// `switch` with a `command_topic` under `{base_topic}/set/{field}`.
+struct DiscoverySwitch {
+    field: &'static str,
+    name: &'static str,
+}
+
+const DISCOVERY_SWITCHES: &[DiscoverySwitch] = &[DiscoverySwitch {
+    field: "ac_relais",
+    name: "ELWA AC-Relais (Netz-Heizstab)",
+}];
+
+#[derive(Serialize)]
+struct HomeAssistantSwitchConfig {
+    name: &'static str,
+    state_topic: String,
+    command_topic: String,
+    unique_id: String,
+}
+
+/// A setpoint that's both reported and settable, surfaced to Home Assistant
+/// as a `number` with a `command_topic` under `{base_topic}/set/{field}`.
+struct DiscoveryNumber {
+    field: &'static str,
+    name: &'static str,
+    unit: &'static str,
+    min: f32,
+    max: f32,
+    step: f32,
+}
+
+const DISCOVERY_NUMBERS: &[DiscoveryNumber] = &[
+    DiscoveryNumber {
+        field: "solltemp_solar",
+        name: "ELWA Solltemperatur Solar",
+        unit: "°C",
+        min: 0.0,
+        max: 95.0,
+        step: 0.5,
+    },
+    DiscoveryNumber {
+        field: "solltemp_netz",
+        name: "ELWA Solltemperatur Netz",
+        unit: "°C",
+        min: 0.0,
+        max: 95.0,
+        step: 0.5,
+    },
+];
+
+#[derive(Serialize)]
+struct HomeAssistantNumberConfig {
+    name: &'static str,
+    state_topic: String,
+    command_topic: String,
+    unit_of_measurement: &'static str,
+    min: f32,
+    max: f32,
+    step: f32,
+    unique_id: String,
+}
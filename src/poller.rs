@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::RwLock;
+
+use crate::device::{self, Status};
+use crate::error::DeviceError;
+use crate::history::{self, SharedDb};
+use crate::mqtt::MqttPublisher;
+
+/// The most recently polled device status, plus when it was taken.
+#[derive(Debug, Clone)]
+pub struct Reading {
+    pub status: Status,
+    pub polled_at: Instant,
+}
+
+impl Reading {
+    pub fn age(&self) -> Duration {
+        self.polled_at.elapsed()
+    }
+}
+
+/// Shared, continuously-refreshed snapshot of the last successful device read.
+///
+/// The read-only request handlers only ever read this cache; they never talk
+/// to the serial port directly, so a slow or hung device can't stall them.
+/// The control routes are the exception: they send a write command over the
+/// serial port and then store the status it returns here.
+pub type SharedReading = Arc<RwLock<Option<Reading>>>;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Polls the serial port and keeps `shared` up to date forever.
+///
+/// On a read error the interval backs off exponentially (capped at
+/// [`MAX_BACKOFF`]) instead of hammering a device that's temporarily
+/// unplugged or mid-reboot, and resets to [`POLL_INTERVAL`] as soon as a
+/// read succeeds again. When `mqtt` is set, every successful reading is also
+/// mirrored onto the broker, and every successful reading is persisted into
+/// `db` for the `/history` endpoint.
+pub async fn run(shared: SharedReading, db: SharedDb, mut mqtt: Option<MqttPublisher>) {
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let poll_result = tokio::task::spawn_blocking(device::read_device).await;
+
+        let status = match poll_result {
+            Ok(Ok(data)) => device::parse_status(&data),
+            Ok(Err(err)) => Err(err),
+            Err(join_err) => Err(DeviceError::SerialIo(join_err.into())),
+        };
+
+        match status {
+            Ok(status) => {
+                if let Some(publisher) = mqtt.as_mut() {
+                    if let Err(err) = publisher.publish(&status).await {
+                        log::warn!("Could not publish reading to MQTT: {err:#}");
+                    }
+                }
+
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64;
+                let db_for_write = db.clone();
+                let status_for_write = status.clone();
+                let record_result = tokio::task::spawn_blocking(move || {
+                    history::record(&db_for_write, now, &status_for_write)
+                })
+                .await;
+                if let Err(err) = record_result.unwrap_or_else(|join_err| Err(join_err.into())) {
+                    log::warn!("Could not record reading to history database: {err:#}");
+                }
+
+                *shared.write().await = Some(Reading {
+                    status,
+                    polled_at: Instant::now(),
+                });
+                backoff = MIN_BACKOFF;
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            Err(err) => {
+                log::warn!("Could not poll device, retrying in {backoff:?}: {err:#}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
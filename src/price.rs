@@ -0,0 +1,294 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::history::{self, SharedDb};
+
+/// Configuration for the dynamic electricity price source.
+///
+/// Modeled on Tibber's API: a bearer token plus the home the prices apply
+/// to. `None` (the default) disables the whole pricing subsystem.
+#[derive(Debug, Clone)]
+pub struct PriceConfig {
+    pub api_token: String,
+    pub home_id: String,
+}
+
+impl PriceConfig {
+    /// Builds a config from the environment, returning `None` if pricing is
+    /// not configured (i.e. `ELWA_TIBBER_API_TOKEN` or `ELWA_TIBBER_HOME_ID`
+    /// is unset).
+    pub fn from_env() -> Option<Self> {
+        let api_token = std::env::var("ELWA_TIBBER_API_TOKEN").ok()?;
+        let home_id = std::env::var("ELWA_TIBBER_HOME_ID").ok()?;
+        Some(Self { api_token, home_id })
+    }
+}
+
+/// One hour's spot price, in the home's currency per kWh.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub starts_at: i64,
+    pub total: f64,
+}
+
+/// The latest fetched price curve, shared with request handlers.
+///
+/// Empty when pricing isn't configured or hasn't been fetched successfully
+/// yet.
+pub type SharedPrices = Arc<RwLock<Vec<PricePoint>>>;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+const TIBBER_API_URL: &str = "https://api.tibber.com/v1-beta/gql";
+
+/// Periodically refreshes `shared` with today's and tomorrow's hourly
+/// prices. Tibber only publishes new prices once a day (tomorrow's prices
+/// usually appear in the early afternoon), but polling every
+/// [`REFRESH_INTERVAL`] is cheap and picks that up without guessing at the
+/// exact publish time.
+pub async fn run(shared: SharedPrices, config: PriceConfig) {
+    loop {
+        match fetch_prices(&config).await {
+            Ok(prices) => {
+                *shared.write().await = prices;
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+            Err(err) => {
+                log::warn!(
+                    "Could not refresh electricity prices, retrying in {RETRY_INTERVAL:?}: {err:#}"
+                );
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+async fn fetch_prices(config: &PriceConfig) -> anyhow::Result<Vec<PricePoint>> {
+    #[derive(Serialize)]
+    struct GraphQlRequest {
+        query: String,
+    }
+
+    #[derive(Deserialize)]
+    struct GraphQlResponse {
+        data: Option<ResponseData>,
+    }
+
+    #[derive(Deserialize)]
+    struct ResponseData {
+        viewer: Viewer,
+    }
+
+    #[derive(Deserialize)]
+    struct Viewer {
+        home: Home,
+    }
+
+    #[derive(Deserialize)]
+    struct Home {
+        #[serde(rename = "currentSubscription")]
+        current_subscription: Subscription,
+    }
+
+    #[derive(Deserialize)]
+    struct Subscription {
+        #[serde(rename = "priceInfo")]
+        price_info: PriceInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct PriceInfo {
+        today: Vec<TibberPrice>,
+        tomorrow: Vec<TibberPrice>,
+    }
+
+    #[derive(Deserialize)]
+    struct TibberPrice {
+        total: f64,
+        #[serde(rename = "startsAt")]
+        starts_at: String,
+    }
+
+    let query = format!(
+        r#"{{ viewer {{ home(id: "{}") {{ currentSubscription {{ priceInfo {{
+            today {{ total startsAt }}
+            tomorrow {{ total startsAt }}
+        }} }} }} }} }}"#,
+        config.home_id
+    );
+
+    let client = reqwest::Client::new();
+    let response: GraphQlResponse = client
+        .post(TIBBER_API_URL)
+        .bearer_auth(&config.api_token)
+        .json(&GraphQlRequest { query })
+        .send()
+        .await
+        .context("Could not reach the electricity price API")?
+        .error_for_status()
+        .context("Electricity price API returned an error status")?
+        .json()
+        .await
+        .context("Could not parse electricity price API response")?;
+
+    let price_info = response
+        .data
+        .context("Electricity price API response had no data")?
+        .viewer
+        .home
+        .current_subscription
+        .price_info;
+
+    price_info
+        .today
+        .into_iter()
+        .chain(price_info.tomorrow)
+        .map(|price| {
+            let starts_at = chrono::DateTime::parse_from_rfc3339(&price.starts_at)
+                .context("Could not parse price timestamp")?
+                .timestamp();
+            Ok(PricePoint {
+                starts_at,
+                total: price.total,
+            })
+        })
+        .collect()
+}
+
+/// The price point whose hour covers `now`, if any.
+pub fn price_at(prices: &[PricePoint], now: i64) -> Option<PricePoint> {
+    prices
+        .iter()
+        .filter(|p| p.starts_at <= now && now < p.starts_at + 3600)
+        .copied()
+        .next()
+}
+
+/// A recommendation on whether right now is a good time to let the grid
+/// backup heater run, based on the upcoming price curve.
+#[derive(Debug, Serialize)]
+pub struct HeatingRecommendation {
+    pub current_price: Option<f64>,
+    pub cheapest_upcoming_price: Option<f64>,
+    pub cheapest_upcoming_starts_at: Option<i64>,
+    pub grid_heating_favorable_now: bool,
+}
+
+/// Recommends whether grid backup heating is economically favorable right
+/// now, given the water is below `solltemp_netz` (if it's already at or
+/// above the setpoint, grid heating wouldn't run regardless of price).
+pub fn recommend(
+    prices: &[PricePoint],
+    now: i64,
+    wassertemp_celsius: f32,
+    solltemp_netz_celsius: f32,
+) -> HeatingRecommendation {
+    let upcoming: Vec<PricePoint> = prices
+        .iter()
+        .copied()
+        .filter(|p| p.starts_at + 3600 > now)
+        .collect();
+
+    let current_price = price_at(prices, now).map(|p| p.total);
+    let cheapest = upcoming.iter().min_by(|a, b| a.total.total_cmp(&b.total));
+
+    let needs_heating = wassertemp_celsius < solltemp_netz_celsius;
+
+    let grid_heating_favorable_now = needs_heating
+        && match (current_price, cheapest) {
+            (Some(current), Some(cheapest)) => current <= cheapest.total * 1.1,
+            _ => false,
+        };
+
+    HeatingRecommendation {
+        current_price,
+        cheapest_upcoming_price: cheapest.map(|p| p.total),
+        cheapest_upcoming_starts_at: cheapest.map(|p| p.starts_at),
+        grid_heating_favorable_now,
+    }
+}
+
+/// Sums up today's grid heating cost: for every gap between consecutive
+/// `netzenergie_heute` history samples, the energy drawn in that gap is
+/// priced at whatever spot price was active when it was drawn.
+pub async fn grid_heating_cost_today(
+    db: &SharedDb,
+    prices: &[PricePoint],
+    now: i64,
+) -> anyhow::Result<f64> {
+    let db = db.clone();
+    let samples = tokio::task::spawn_blocking(move || history::netzenergie_heute_today(&db, now))
+        .await
+        .context("History query task panicked")??;
+
+    let mut cost = 0.0;
+    for pair in samples.windows(2) {
+        let [from, to] = pair else { continue };
+        let delta_wh = (to.value - from.value).max(0.0);
+        if delta_wh == 0.0 {
+            continue;
+        }
+        let Some(price) = price_at(prices, from.timestamp) else {
+            continue;
+        };
+        cost += (delta_wh as f64 / 1000.0) * price.total;
+    }
+
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices() -> Vec<PricePoint> {
+        vec![
+            PricePoint {
+                starts_at: 0,
+                total: 0.30,
+            },
+            PricePoint {
+                starts_at: 3600,
+                total: 0.10,
+            },
+            PricePoint {
+                starts_at: 7200,
+                total: 0.20,
+            },
+        ]
+    }
+
+    #[test]
+    fn favorable_when_heating_needed_and_price_near_cheapest_upcoming() {
+        let recommendation = recommend(&prices(), 3600, 40.0, 55.0);
+        assert_eq!(recommendation.current_price, Some(0.10));
+        assert_eq!(recommendation.cheapest_upcoming_price, Some(0.10));
+        assert!(recommendation.grid_heating_favorable_now);
+    }
+
+    #[test]
+    fn not_favorable_when_water_already_at_setpoint() {
+        let recommendation = recommend(&prices(), 3600, 55.0, 55.0);
+        assert!(!recommendation.grid_heating_favorable_now);
+    }
+
+    #[test]
+    fn not_favorable_when_current_price_far_above_cheapest_upcoming() {
+        let recommendation = recommend(&prices(), 0, 40.0, 55.0);
+        assert_eq!(recommendation.current_price, Some(0.30));
+        assert_eq!(recommendation.cheapest_upcoming_price, Some(0.10));
+        assert!(!recommendation.grid_heating_favorable_now);
+    }
+
+    #[test]
+    fn no_recommendation_without_any_price_data() {
+        let recommendation = recommend(&[], 0, 40.0, 55.0);
+        assert_eq!(recommendation.current_price, None);
+        assert!(!recommendation.grid_heating_favorable_now);
+    }
+}